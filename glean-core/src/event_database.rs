@@ -0,0 +1,237 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::Glean;
+
+/// The default maximum number of events that may be stored for a single
+/// store before it is automatically flushed by submitting the "events"
+/// ping and clearing the flushed entries. This keeps long-running sessions
+/// from accumulating an unbounded number of events between pings.
+pub const DEFAULT_MAX_EVENTS_PER_STORE: usize = 500;
+
+/// A single recorded event.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RecordedEvent {
+    /// The number of milliseconds since the store's first recorded event,
+    /// i.e. since the start of the current ping's collection window.
+    pub timestamp: u64,
+    /// The event's category.
+    pub category: String,
+    /// The event's name.
+    pub name: String,
+    /// The event's extra key/value pairs, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extra: Option<HashMap<String, String>>,
+}
+
+/// The events recorded so far for a single store, plus the monotonic-clock
+/// epoch they're timestamped against. The epoch is the `Instant` at which
+/// the store's first event was recorded, so that every event's `timestamp`
+/// is its offset in milliseconds from the start of the current ping's
+/// collection window.
+#[derive(Debug)]
+struct StoreData {
+    epoch: Instant,
+    events: Vec<RecordedEvent>,
+}
+
+impl StoreData {
+    fn new(epoch: Instant) -> Self {
+        Self {
+            epoch,
+            events: Vec::new(),
+        }
+    }
+}
+
+/// The in-memory storage for events, keyed by the store (ping) name they
+/// were recorded into.
+///
+/// Each store is capped at `max_events_per_store` entries. Once `record`
+/// brings a store up to that threshold, the "events" ping for that store is
+/// submitted immediately and the flushed entries are cleared, so that
+/// events recorded afterwards start a fresh batch, with its own epoch.
+#[derive(Debug)]
+pub struct EventDatabase {
+    event_stores: RwLock<HashMap<String, StoreData>>,
+    max_events_per_store: usize,
+}
+
+impl EventDatabase {
+    /// Creates a new, empty event database with the default per-store
+    /// capacity. Callers that have a configured capacity (e.g. a
+    /// `max_events` override threaded in from `Glean`'s init configuration)
+    /// should use `with_max_events_per_store` instead.
+    pub fn new() -> Self {
+        Self::with_max_events_per_store(DEFAULT_MAX_EVENTS_PER_STORE)
+    }
+
+    /// Creates a new, empty event database with a configured per-store
+    /// capacity. This is the constructor to use when a capacity is
+    /// available from configuration rather than the default; it's also used
+    /// directly by tests that want to exercise the flush behavior without
+    /// recording hundreds of events.
+    pub fn with_max_events_per_store(max_events_per_store: usize) -> Self {
+        Self {
+            event_stores: RwLock::new(HashMap::new()),
+            max_events_per_store,
+        }
+    }
+
+    /// Records an event into the named store, timestamping it relative to
+    /// the store's epoch (the first event recorded since the store was last
+    /// empty), and flushing the store by submitting its ping if this push
+    /// reached the configured capacity.
+    pub fn record(
+        &self,
+        glean: &Glean,
+        store_name: &str,
+        category: String,
+        name: String,
+        extra: Option<HashMap<String, String>>,
+    ) {
+        // The push and the capacity check-and-clear must happen under the
+        // same lock acquisition: if they were two separate critical
+        // sections, two threads racing past the threshold on the same store
+        // could each see `at_capacity` and both flush, or an event recorded
+        // in the gap between sections could be pulled into the flushed ping
+        // and discarded without ever being timestamped against the next
+        // epoch.
+        let reached_capacity = {
+            let mut db = self
+                .event_stores
+                .write()
+                .expect("lock of event store was poisoned");
+            let store = db
+                .entry(store_name.to_string())
+                .or_insert_with(|| StoreData::new(Instant::now()));
+            let timestamp = Instant::now().duration_since(store.epoch).as_millis() as u64;
+            store.events.push(RecordedEvent {
+                timestamp,
+                category,
+                name,
+                extra,
+            });
+            let reached_capacity = store.events.len() >= self.max_events_per_store;
+            if reached_capacity {
+                db.remove(store_name);
+            }
+            reached_capacity
+        };
+
+        if reached_capacity {
+            glean.submit_ping_by_name(store_name, Some("max_capacity"));
+        }
+    }
+
+    /// **Exported for test purposes.**
+    ///
+    /// Tests whether there are currently stored events for the given store.
+    pub fn test_has_value(&self, store_name: &str) -> bool {
+        self.event_stores
+            .read()
+            .expect("lock of event store was poisoned")
+            .get(store_name)
+            .map_or(false, |store| !store.events.is_empty())
+    }
+
+    /// **Exported for test purposes.**
+    ///
+    /// Gets the currently stored events for the given store, each
+    /// timestamped with its offset in milliseconds from the store's first
+    /// recorded event.
+    pub fn test_get_value(&self, store_name: &str) -> Option<Vec<RecordedEvent>> {
+        self.event_stores
+            .read()
+            .expect("lock of event store was poisoned")
+            .get(store_name)
+            .map(|store| store.events.clone())
+    }
+
+    /// **Exported for test purposes.**
+    ///
+    /// Gets the currently stored events for the given store as a
+    /// JSON-encoded string.
+    pub fn test_get_value_as_json_string(&self, store_name: &str) -> String {
+        json!(self.test_get_value(store_name).unwrap_or_default()).to_string()
+    }
+}
+
+impl Default for EventDatabase {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn record(db: &EventDatabase, glean: &Glean, name: &str) {
+        record_into(db, glean, "events", name);
+    }
+
+    fn record_into(db: &EventDatabase, glean: &Glean, store_name: &str, name: &str) {
+        db.record(glean, store_name, "test".into(), name.into(), None);
+    }
+
+    #[test]
+    fn flushes_and_starts_a_fresh_batch_at_capacity() {
+        let glean = Glean::new_for_tests();
+        let db = EventDatabase::with_max_events_per_store(3);
+
+        record(&db, &glean, "one");
+        record(&db, &glean, "two");
+        assert!(db.test_has_value("events"));
+        assert_eq!(2, db.test_get_value("events").unwrap().len());
+
+        // This push reaches the configured capacity and triggers a flush.
+        record(&db, &glean, "three");
+        assert!(!db.test_has_value("events"));
+        assert!(glean.test_was_ping_submitted("events"));
+
+        // Events recorded after the flush start a new batch, timestamped
+        // relative to a fresh epoch.
+        record(&db, &glean, "four");
+        assert!(db.test_has_value("events"));
+        let events = db.test_get_value("events").unwrap();
+        assert_eq!(1, events.len());
+        assert_eq!(0, events[0].timestamp);
+    }
+
+    #[test]
+    fn flushes_the_store_that_actually_hit_capacity() {
+        let glean = Glean::new_for_tests();
+        let db = EventDatabase::with_max_events_per_store(3);
+
+        record_into(&db, &glean, "custom-ping", "one");
+        record_into(&db, &glean, "custom-ping", "two");
+        record_into(&db, &glean, "custom-ping", "three");
+
+        assert!(!db.test_has_value("custom-ping"));
+        assert!(glean.test_was_ping_submitted("custom-ping"));
+        assert!(!glean.test_was_ping_submitted("events"));
+    }
+
+    #[test]
+    fn timestamps_are_monotonic_offsets_from_the_first_event() {
+        let glean = Glean::new_for_tests();
+        let db = EventDatabase::new();
+
+        record(&db, &glean, "one");
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        record(&db, &glean, "two");
+
+        let events = db.test_get_value("events").unwrap();
+        assert_eq!(0, events[0].timestamp);
+        assert!(events[1].timestamp >= 5);
+    }
+}
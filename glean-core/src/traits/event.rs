@@ -3,23 +3,63 @@
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
 use std::collections::HashMap;
+use std::hash::Hash;
 
 use crate::event_database::RecordedEvent;
 
+/// The set of valid extra keys for an event metric.
+///
+/// This is implemented by the generated enum for each event metric that
+/// defines `allowed_extra_keys`, so that an `extra` key typo or an
+/// out-of-range index is caught at compile time rather than being reported
+/// as a runtime error. `NoExtraKeys` is used for event metrics that don't
+/// define any extra keys.
+pub trait ExtraKeys: Hash + Eq + Copy {
+    /// The allowed extra keys, in the same order as the metric's
+    /// `allowed_extra_keys`. A variant's position in this slice is its wire
+    /// index, as returned by `index()`.
+    const ALLOWED_KEYS: &'static [&'static str];
+
+    /// The wire index of this key, i.e. its position in `ALLOWED_KEYS`.
+    ///
+    /// Returns `-1` for an invalid key, which continues to be reported as an
+    /// error and results in no event being recorded.
+    fn index(self) -> i32;
+}
+
+/// Default of the `ExtraKeys` trait, used for events that don't have any
+/// extra keys.
+///
+/// This is an empty enum, as there are no valid instances of it.
+#[derive(Clone, Copy, Debug, Hash, Eq, PartialEq)]
+pub enum NoExtraKeys {}
+
+impl ExtraKeys for NoExtraKeys {
+    const ALLOWED_KEYS: &'static [&'static str] = &[];
+
+    fn index(self) -> i32 {
+        match self {}
+    }
+}
+
 /// A description for the `EventMetric` type.
 ///
 /// When changing this trait, make sure all the operations are
 /// implemented in the related type in `../metrics/`.
 pub trait Event {
+    /// The type of the allowed extra keys for this event.
+    type Extra: ExtraKeys;
+
     /// Records an event.
     ///
     /// # Arguments
     ///
-    /// * `extra` - A HashMap of (key, value) pairs. The key is an index into
-    ///   the metric's `allowed_extra_keys` vector where the key's string is
-    ///   looked up. If any key index is out of range, an error is reported and
-    ///   no event is recorded.
-    fn record<M: Into<Option<HashMap<i32, String>>>>(&self, extra: M);
+    /// * `extra` - A HashMap of (key, value) pairs. The key is an enum value
+    ///   from `Self::Extra`, whose `index()` identifies the metric's
+    ///   `allowed_extra_keys` entry where the key's string is looked up. If
+    ///   any key's index is invalid (i.e. `-1`), an error is reported and no
+    ///   event is recorded.
+    fn record<M: Into<Option<HashMap<Self::Extra, String>>>>(&self, extra: M);
 
     /// **Exported for test purposes.**
     ///